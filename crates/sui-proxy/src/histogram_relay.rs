@@ -1,37 +1,169 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
-use anyhow::{bail, Result};
-use axum::{extract::Extension, http::StatusCode, routing::get, Router};
+use anyhow::{anyhow, bail, Context, Result};
+use arc_swap::ArcSwap;
+use axum::{
+    extract::{Extension, State},
+    http::{header::AUTHORIZATION, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
 use mysten_metrics::RegistryService;
 use prometheus::{
     proto::{Metric, MetricFamily},
     TextEncoder,
 };
+use socket2::{Domain, Socket, Type};
 use std::{
-    collections::VecDeque,
-    net::SocketAddr,
-    sync::{Arc, RwLock},
+    collections::HashMap,
+    net::{SocketAddr, TcpListener},
+    path::PathBuf,
+    sync::Arc,
 };
+use subtle::ConstantTimeEq;
 use tracing::warn;
 
 const METRICS_ROUTE: &str = "/metrics";
 
+// Configures bearer-token auth on the /metrics endpoint. The secret can be supplied
+// inline or via secret_file (read once at startup, trimmed). When neither is set the
+// endpoint stays open, preserving the previous behavior.
+#[derive(Clone, Debug, Default)]
+pub struct MetricsAuthConfig {
+    pub secret: Option<String>,
+    pub secret_file: Option<PathBuf>,
+}
+
+impl MetricsAuthConfig {
+    fn resolve_secret(&self) -> Result<Option<String>> {
+        match (&self.secret, &self.secret_file) {
+            (Some(_), Some(_)) => {
+                Err(anyhow!("only one of `secret` or `secret_file` may be set"))
+            }
+            (Some(secret), None) => Ok(Some(secret.clone())),
+            (None, Some(path)) => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read metrics secret from {path:?}"))?;
+                Ok(Some(contents.trim().to_owned()))
+            }
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+// Number of pushed snapshots retained for merging on scrape, absent an explicit
+// capacity from `HistogramRelay::new_with_capacity`. Once the ring is full, submit
+// drops the oldest snapshot to make room for the newest.
+const DEFAULT_RETENTION: usize = 8;
+
+// Accepted by `start_prometheus_server`: either a single address (so existing
+// callers passing a bare `SocketAddr` keep compiling unchanged) or a `Vec` or array
+// of addresses, e.g. an IPv4 and an IPv6 wildcard bound to the same port.
+pub enum PrometheusServerAddrs {
+    One(SocketAddr),
+    Many(Vec<SocketAddr>),
+}
+
+impl From<SocketAddr> for PrometheusServerAddrs {
+    fn from(addr: SocketAddr) -> Self {
+        Self::One(addr)
+    }
+}
+
+impl From<Vec<SocketAddr>> for PrometheusServerAddrs {
+    fn from(addrs: Vec<SocketAddr>) -> Self {
+        Self::Many(addrs)
+    }
+}
+
+impl<const N: usize> From<[SocketAddr; N]> for PrometheusServerAddrs {
+    fn from(addrs: [SocketAddr; N]) -> Self {
+        Self::Many(addrs.into_iter().collect())
+    }
+}
+
+impl IntoIterator for PrometheusServerAddrs {
+    type Item = SocketAddr;
+    type IntoIter = std::vec::IntoIter<SocketAddr>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Self::One(addr) => vec![addr].into_iter(),
+            Self::Many(addrs) => addrs.into_iter(),
+        }
+    }
+}
+
+// Binds a listener for `addr`. IPv6 sockets have `IPV6_V6ONLY` set explicitly
+// rather than relying on the platform default, so binding an IPv4 wildcard and an
+// IPv6 wildcard to the same port (the multi-address case above) doesn't race
+// against a dual-stack IPv6 socket claiming the port first.
+fn bind_listener(addr: SocketAddr) -> Result<TcpListener> {
+    let domain = Domain::for_address(addr);
+    let socket = Socket::new(domain, Type::STREAM, None)
+        .with_context(|| format!("failed to create socket for {addr}"))?;
+    if domain == Domain::IPV6 {
+        socket
+            .set_only_v6(true)
+            .with_context(|| format!("failed to set IPV6_V6ONLY on {addr}"))?;
+    }
+    socket
+        .bind(&addr.into())
+        .with_context(|| format!("failed to bind metrics listener on {addr}"))?;
+    socket
+        .listen(1024)
+        .with_context(|| format!("failed to listen on {addr}"))?;
+    socket
+        .set_nonblocking(true)
+        .with_context(|| format!("failed to configure metrics listener on {addr}"))?;
+    Ok(socket.into())
+}
+
 // Creates a new http server that has as a sole purpose to expose
 // and endpoint that prometheus agent can use to poll for the metrics.
 // A RegistryService is returned that can be used to get access in prometheus Registries.
-pub fn start_prometheus_server(addr: SocketAddr) -> HistogramRelay {
+//
+// All addresses are bound before this function returns, so a bind conflict is
+// reported as an error here rather than panicking a spawned task.
+pub fn start_prometheus_server(
+    addrs: impl Into<PrometheusServerAddrs>,
+    auth_config: MetricsAuthConfig,
+) -> Result<HistogramRelay> {
     let relay = HistogramRelay::new();
-    let app = Router::new()
+    let secret = auth_config.resolve_secret()?;
+    let mut app = Router::new()
         .route(METRICS_ROUTE, get(metrics))
         .layer(Extension(relay.clone()));
+    if let Some(secret) = secret {
+        app = app.layer(middleware::from_fn_with_state(
+            Arc::new(secret),
+            require_bearer_auth,
+        ));
+    }
+
+    let mut listeners = Vec::new();
+    for addr in addrs.into() {
+        listeners.push((addr, bind_listener(addr)?));
+    }
+    if listeners.is_empty() {
+        bail!("start_prometheus_server requires at least one address to bind");
+    }
 
-    tokio::spawn(async move {
-        axum::Server::bind(&addr)
-            .serve(app.into_make_service())
-            .await
-            .unwrap();
-    });
-    relay
+    for (addr, listener) in listeners {
+        let app = app.clone();
+        tokio::spawn(async move {
+            let result = axum::Server::from_tcp(listener)
+                .expect("listener was already configured for non-blocking use")
+                .serve(app.into_make_service())
+                .await;
+            if let Err(error) = result {
+                warn!("metrics server on {addr} exited with error: {error}");
+            }
+        });
+    }
+    Ok(relay)
 }
 
 async fn metrics(Extension(relay): Extension<HistogramRelay>) -> (StatusCode, String) {
@@ -44,29 +176,75 @@ async fn metrics(Extension(relay): Extension<HistogramRelay>) -> (StatusCode, St
     (StatusCode::OK, expformat)
 }
 
+// Constant-time so an attacker probing the endpoint can't use response timing to
+// recover the secret byte by byte.
+fn is_authorized(headers: &axum::http::HeaderMap, secret: &str) -> bool {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|token| bool::from(token.as_bytes().ct_eq(secret.as_bytes())))
+        .unwrap_or(false)
+}
+
+async fn require_bearer_auth<B>(
+    State(secret): State<Arc<String>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if !is_authorized(request.headers(), &secret) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+// A single pushed batch of metric families, retained in the relay's ring until newer
+// submissions push it out; every scrape merges all snapshots still retained.
+type Snapshot = Vec<MetricFamily>;
+
+// Relays histogram snapshots pushed by `submit` to the `/metrics` scrape path.
+// The retained ring lives behind an ArcSwap, so submit and export never block each
+// other, and is capped at `capacity` snapshots so a slow or absent scraper can't
+// grow memory without bound.
 #[derive(Clone)]
-pub struct HistogramRelay(Arc<RwLock<VecDeque<Vec<MetricFamily>>>>);
+pub struct HistogramRelay {
+    snapshots: Arc<ArcSwap<Vec<Snapshot>>>,
+    capacity: usize,
+}
 
 impl HistogramRelay {
     pub fn new() -> Self {
-        HistogramRelay(Arc::new(RwLock::new(VecDeque::new())))
+        Self::new_with_capacity(DEFAULT_RETENTION)
     }
-    pub fn submit(&self, data: Vec<MetricFamily>) {
-        self.0
-            .write()
-            .expect("couldn't get mut lock on HistogramRelay")
-            .push_back(data);
+
+    pub fn new_with_capacity(capacity: usize) -> Self {
+        HistogramRelay {
+            snapshots: Arc::new(ArcSwap::from_pointee(Vec::new())),
+            capacity: capacity.max(1),
+        }
     }
+
+    pub fn submit(&self, data: Snapshot) {
+        let capacity = self.capacity;
+        self.snapshots.rcu(|current| {
+            let mut next = current.as_ref().clone();
+            next.push(data.clone());
+            if next.len() > capacity {
+                let excess = next.len() - capacity;
+                next.drain(0..excess);
+            }
+            next
+        });
+    }
+
     pub fn export(&self) -> Result<String> {
-        let Some(data) = self
-            .0
-            .write()
-            .expect("couldn't get mut lock on HistogramRelay")
-            .pop_front() else {
-                warn!("no data in HistogramRelay buffer, this may be ok...");
-                bail!("no data in HistogramRelay to scrape")
-            };
-        let histograms: Vec<MetricFamily> = extract_histograms(data).collect();
+        let snapshots = self.snapshots.load();
+        if snapshots.is_empty() {
+            warn!("no data in HistogramRelay buffer, this may be ok...");
+            bail!("no data in HistogramRelay to scrape")
+        }
+        let merged = merge_snapshots(snapshots.iter());
+        let histograms: Vec<MetricFamily> = extract_histograms(merged).collect();
         let encoder = prometheus::TextEncoder::new();
         let string = match encoder.encode_to_string(&histograms) {
             Ok(s) => s,
@@ -76,6 +254,59 @@ impl HistogramRelay {
     }
 }
 
+// Merges retained snapshots into a single set of metric families. Each submit() is a
+// full push of a source's current state under the same name/label set, so appending
+// blindly would emit the same name+labels more than once per family once two snapshots
+// from the same source are retained - invalid Prometheus exposition format. Instead,
+// dedupe per exact label set, keeping the value from the most recent snapshot.
+fn merge_snapshots<'a>(snapshots: impl Iterator<Item = &'a Snapshot>) -> Vec<MetricFamily> {
+    let mut by_name: HashMap<String, (MetricFamily, HashMap<String, Metric>)> = HashMap::new();
+    for snapshot in snapshots {
+        for mf in snapshot {
+            let (_, metrics_by_label) = by_name
+                .entry(mf.get_name().to_owned())
+                .or_insert_with(|| (family_template(mf), HashMap::new()));
+            for metric in mf.get_metric() {
+                metrics_by_label.insert(label_set_key(metric), metric.clone());
+            }
+        }
+    }
+    by_name
+        .into_values()
+        .map(|(mut family, metrics_by_label)| {
+            family.set_metric(protobuf::RepeatedField::from_iter(
+                metrics_by_label.into_values(),
+            ));
+            family
+        })
+        .collect()
+}
+
+// A MetricFamily with the same name/help/type as `mf` but no metric points, used as the
+// starting point for rebuilding a family from deduped points in `merge_snapshots`.
+fn family_template(mf: &MetricFamily) -> MetricFamily {
+    let mut v = MetricFamily::default();
+    v.set_name(mf.get_name().to_owned());
+    v.set_help(mf.get_help().to_owned());
+    v.set_field_type(mf.get_field_type());
+    v
+}
+
+// A stable key identifying a metric's exact label set, regardless of label order.
+fn label_set_key(metric: &Metric) -> String {
+    let mut pairs: Vec<(&str, &str)> = metric
+        .get_label()
+        .iter()
+        .map(|l| (l.get_name(), l.get_value()))
+        .collect();
+    pairs.sort_unstable();
+    pairs
+        .into_iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 fn extract_histograms(data: Vec<MetricFamily>) -> impl Iterator<Item = MetricFamily> {
     data.into_iter().map(|mf| {
         let metrics = mf.get_metric().iter().map(|m| {
@@ -93,3 +324,102 @@ fn extract_histograms(data: Vec<MetricFamily>) -> impl Iterator<Item = MetricFam
         v
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::proto::{Histogram, LabelPair, MetricType};
+
+    fn histogram_family(name: &str, label_value: &str, sample_count: u64) -> MetricFamily {
+        let mut label = LabelPair::default();
+        label.set_name("source".to_owned());
+        label.set_value(label_value.to_owned());
+
+        let mut histogram = Histogram::default();
+        histogram.set_sample_count(sample_count);
+        histogram.set_sample_sum(sample_count as f64);
+
+        let mut metric = Metric::default();
+        metric.set_label(protobuf::RepeatedField::from_vec(vec![label]));
+        metric.set_histogram(histogram);
+
+        let mut family = MetricFamily::default();
+        family.set_name(name.to_owned());
+        family.set_field_type(MetricType::HISTOGRAM);
+        family.set_metric(protobuf::RepeatedField::from_vec(vec![metric]));
+        family
+    }
+
+    #[test]
+    fn export_dedupes_repeated_label_sets_across_snapshots() {
+        let relay = HistogramRelay::new();
+        relay.submit(vec![histogram_family("request_latency", "svc-a", 1)]);
+        relay.submit(vec![histogram_family("request_latency", "svc-a", 2)]);
+
+        let exported = relay.export().unwrap();
+        let count_lines: Vec<&str> = exported
+            .lines()
+            .filter(|line| line.starts_with("request_latency_count"))
+            .collect();
+        assert_eq!(
+            count_lines.len(),
+            1,
+            "expected exactly one sample per label set, got:\n{exported}"
+        );
+        assert!(
+            count_lines[0].ends_with(" 2"),
+            "expected the latest submission to win, got:\n{exported}"
+        );
+    }
+
+    #[test]
+    fn export_keeps_distinct_label_sets_separate() {
+        let relay = HistogramRelay::new();
+        relay.submit(vec![histogram_family("request_latency", "svc-a", 1)]);
+        relay.submit(vec![histogram_family("request_latency", "svc-b", 1)]);
+
+        let exported = relay.export().unwrap();
+        let count_lines = exported
+            .lines()
+            .filter(|line| line.starts_with("request_latency_count"))
+            .count();
+        assert_eq!(count_lines, 2, "got:\n{exported}");
+    }
+
+    #[test]
+    fn resolve_secret_rejects_both_inline_and_file() {
+        let config = MetricsAuthConfig {
+            secret: Some("inline".to_owned()),
+            secret_file: Some(PathBuf::from("/dev/null")),
+        };
+        assert!(config.resolve_secret().is_err());
+    }
+
+    #[test]
+    fn resolve_secret_reads_and_trims_file() {
+        let path =
+            std::env::temp_dir().join(format!("sui-proxy-metrics-secret-test-{}", std::process::id()));
+        std::fs::write(&path, "s3cr3t\n").unwrap();
+        let config = MetricsAuthConfig {
+            secret: None,
+            secret_file: Some(path.clone()),
+        };
+        let result = config.resolve_secret().unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(result, Some("s3cr3t".to_owned()));
+    }
+
+    #[test]
+    fn resolve_secret_none_when_unset() {
+        assert_eq!(MetricsAuthConfig::default().resolve_secret().unwrap(), None);
+    }
+
+    #[test]
+    fn is_authorized_requires_matching_bearer_token() {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(AUTHORIZATION, "Bearer s3cr3t".parse().unwrap());
+        assert!(is_authorized(&headers, "s3cr3t"));
+        assert!(!is_authorized(&headers, "other"));
+        assert!(!is_authorized(&axum::http::HeaderMap::new(), "s3cr3t"));
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use mysten_metrics::RegistryService;
+use prometheus::{IntGauge, IntGaugeVec};
+use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{Pid, PidExt, ProcessExt, System, SystemExt};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+// Configuration for the background process/RocksDB telemetry sampler. Gauges are only
+// registered once start_system_metrics_collector is actually called, so a binary that
+// never opts in pays no cost.
+#[derive(Clone, Debug)]
+pub struct SystemMetricsConfig {
+    /// How often to resample process and RocksDB gauges.
+    pub sampling_interval: Duration,
+    /// The pid to sample; defaults to the current process if unset.
+    pub pid: Option<Pid>,
+}
+
+impl Default for SystemMetricsConfig {
+    fn default() -> Self {
+        Self {
+            sampling_interval: Duration::from_secs(15),
+            pid: None,
+        }
+    }
+}
+
+/// A RocksDB column family to sample compaction stats for.
+pub struct ColumnFamilyMetricsSource {
+    pub db_name: String,
+    pub cf_name: String,
+    pub db: Arc<rocksdb::DB>,
+}
+
+struct SystemMetricsGauges {
+    cpu_usage_millicores: IntGauge,
+    mem_resident_bytes: IntGauge,
+    mem_virtual_bytes: IntGauge,
+    open_fds: IntGauge,
+    num_threads: IntGauge,
+    rocksdb_compaction_pending: IntGaugeVec,
+    rocksdb_running_compactions: IntGaugeVec,
+}
+
+impl SystemMetricsGauges {
+    fn new(registry_service: &RegistryService) -> Self {
+        let registry = registry_service.default_registry();
+        Self {
+            cpu_usage_millicores: mysten_metrics::register_int_gauge_with_registry!(
+                "process_cpu_usage_millicores",
+                "Process CPU usage in millicores, sampled on an interval",
+                &registry,
+            )
+            .unwrap(),
+            mem_resident_bytes: mysten_metrics::register_int_gauge_with_registry!(
+                "process_resident_memory_bytes",
+                "Process resident set size in bytes",
+                &registry,
+            )
+            .unwrap(),
+            mem_virtual_bytes: mysten_metrics::register_int_gauge_with_registry!(
+                "process_virtual_memory_bytes",
+                "Process virtual memory size in bytes",
+                &registry,
+            )
+            .unwrap(),
+            open_fds: mysten_metrics::register_int_gauge_with_registry!(
+                "process_open_fds",
+                "Number of open file descriptors held by the process",
+                &registry,
+            )
+            .unwrap(),
+            num_threads: mysten_metrics::register_int_gauge_with_registry!(
+                "process_num_threads",
+                "Number of OS threads owned by the process",
+                &registry,
+            )
+            .unwrap(),
+            rocksdb_compaction_pending: mysten_metrics::register_int_gauge_vec_with_registry!(
+                "rocksdb_compaction_pending",
+                "Whether RocksDB reports a pending compaction for a column family (1/0)",
+                &["db_name", "cf_name"],
+                &registry,
+            )
+            .unwrap(),
+            rocksdb_running_compactions: mysten_metrics::register_int_gauge_vec_with_registry!(
+                "rocksdb_running_compactions",
+                "Number of compactions currently running against a column family",
+                &["db_name", "cf_name"],
+                &registry,
+            )
+            .unwrap(),
+        }
+    }
+}
+
+// Spawns a background task that samples process and RocksDB telemetry on an interval
+// and publishes the results as gauges on `registry_service`.
+pub fn start_system_metrics_collector(
+    registry_service: &RegistryService,
+    config: SystemMetricsConfig,
+    column_families: Vec<ColumnFamilyMetricsSource>,
+) -> JoinHandle<()> {
+    let gauges = SystemMetricsGauges::new(registry_service);
+    let pid = config
+        .pid
+        .unwrap_or_else(|| Pid::from_u32(std::process::id()));
+
+    tokio::spawn(async move {
+        let mut system = System::new();
+        let mut interval = tokio::time::interval(config.sampling_interval);
+        loop {
+            interval.tick().await;
+            system.refresh_process(pid);
+            let Some(process) = system.process(pid) else {
+                warn!("system_metrics: no process found for pid {pid}, skipping sample");
+                continue;
+            };
+            gauges
+                .cpu_usage_millicores
+                .set((process.cpu_usage() * 10.0) as i64);
+            gauges.mem_resident_bytes.set(process.memory() as i64);
+            gauges.mem_virtual_bytes.set(process.virtual_memory() as i64);
+            gauges.num_threads.set(num_threads(pid));
+            gauges.open_fds.set(num_open_fds(pid));
+
+            for cf in &column_families {
+                let Some(handle) = cf_handle(&cf.db, &cf.cf_name) else {
+                    warn!(
+                        "system_metrics: no column family {:?} in {:?}, skipping sample",
+                        cf.cf_name, cf.db_name
+                    );
+                    continue;
+                };
+                let labels: &[&str] = &[&cf.db_name, &cf.cf_name];
+                if let Ok(Some(pending)) =
+                    cf.db.property_int_value_cf(handle, "rocksdb.compaction-pending")
+                {
+                    gauges
+                        .rocksdb_compaction_pending
+                        .with_label_values(labels)
+                        .set(pending as i64);
+                }
+                if let Ok(Some(running)) =
+                    cf.db.property_int_value_cf(handle, "rocksdb.num-running-compactions")
+                {
+                    gauges
+                        .rocksdb_running_compactions
+                        .with_label_values(labels)
+                        .set(running as i64);
+                }
+            }
+        }
+    })
+}
+
+fn cf_handle<'a>(db: &'a rocksdb::DB, cf_name: &str) -> Option<&'a rocksdb::ColumnFamily> {
+    db.cf_handle(cf_name)
+}
+
+fn count_dir_entries(dir: impl AsRef<std::path::Path>) -> i64 {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.count() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(target_os = "linux")]
+fn num_open_fds(pid: Pid) -> i64 {
+    count_dir_entries(format!("/proc/{}/fd", pid.as_u32()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn num_open_fds(_pid: Pid) -> i64 {
+    0
+}
+
+// Field 20 (1-indexed) of /proc/pid/stat, i.e. index 17 of the whitespace-split
+// fields after `comm`. Splitting on the *last* `)` rather than the first means a
+// `comm` that itself contains `)` (rare, but the kernel allows arbitrary bytes
+// there) doesn't throw off the field count.
+fn parse_num_threads(stat_contents: &str) -> i64 {
+    stat_contents
+        .rsplit(')')
+        .next()
+        .and_then(|rest| rest.split_whitespace().nth(17))
+        .and_then(|field| field.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(target_os = "linux")]
+fn num_threads(pid: Pid) -> i64 {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid.as_u32()))
+        .ok()
+        .map(|stat| parse_num_threads(&stat))
+        .unwrap_or(0)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn num_threads(_pid: Pid) -> i64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_num_threads_reads_field_20() {
+        let stat = "12345 (my proc) S 1 12345 12345 0 -1 4194304 100 0 0 0 1 2 0 0 20 0 \
+                     7 0 1000 100000 200 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 \
+                     17 3 0 0 0 0 0 0 0 0 0 0 0 0 0";
+        assert_eq!(parse_num_threads(stat), 7);
+    }
+
+    #[test]
+    fn parse_num_threads_handles_parens_in_comm() {
+        let stat = "12345 (proc (fancy) name) S 1 12345 12345 0 -1 4194304 100 0 0 0 1 2 0 0 20 0 \
+                     3 0 1000 100000 200 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 \
+                     17 3 0 0 0 0 0 0 0 0 0 0 0 0 0";
+        assert_eq!(parse_num_threads(stat), 3);
+    }
+
+    #[test]
+    fn parse_num_threads_defaults_to_zero_on_malformed_input() {
+        assert_eq!(parse_num_threads("not a stat line"), 0);
+    }
+
+    #[test]
+    fn count_dir_entries_counts_files_in_directory() {
+        let dir = std::env::temp_dir().join(format!(
+            "sui-proxy-system-metrics-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["a", "b", "c"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        assert_eq!(count_dir_entries(&dir), 3);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,382 @@
+// Copyright (c) Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use hdrhistogram::Histogram;
+use rocksdb::{Direction, IteratorMode, Options, DB};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use sui_types::base_types::EpochId;
+use tracing::warn;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub enum StoreName {
+    Perpetual,
+    Checkpoints,
+}
+
+impl StoreName {
+    fn db_path(self, db_path: &Path, _epoch: Option<EpochId>) -> PathBuf {
+        match self {
+            // Mirrors the layout `reset_db_to_genesis` already assumes for these stores.
+            StoreName::Perpetual => db_path.join("store").join("perpetual"),
+            StoreName::Checkpoints => db_path.join("checkpoints"),
+        }
+    }
+
+    fn open_read_only(self, db_path: &Path, epoch: Option<EpochId>) -> Result<DB> {
+        let path = self.db_path(db_path, epoch);
+        let cf_names = DB::list_cf(&Options::default(), &path)
+            .with_context(|| format!("failed to list column families at {path:?}"))?;
+        DB::open_cf_for_read_only(&Options::default(), &path, &cf_names, false)
+            .with_context(|| format!("failed to open {path:?} read-only"))
+    }
+}
+
+/// A single key/value pair read out of a column family. `Decoded` is used when the
+/// table's schema is known and the bytes could be rendered into a human-readable form;
+/// `Raw` is the fallback for unknown tables. `Decoded` keeps the original on-disk bytes
+/// alongside the rendered strings, so `into_raw_bytes` (backing the `Bcs` format) never
+/// has to round-trip through the human-readable representation.
+pub enum DumpEntry {
+    Decoded {
+        key: String,
+        value: String,
+        raw_key: Vec<u8>,
+        raw_value: Vec<u8>,
+    },
+    Raw {
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+}
+
+impl DumpEntry {
+    pub fn as_json(&self) -> serde_json::Value {
+        match self {
+            DumpEntry::Decoded { key, value, .. } => json!({ "key": key, "value": value }),
+            DumpEntry::Raw { key, value } => {
+                json!({ "key": hex::encode(key), "value": hex::encode(value) })
+            }
+        }
+    }
+
+    pub fn as_hex_pair(&self) -> (String, String) {
+        match self {
+            DumpEntry::Decoded { key, value, .. } => (key.clone(), value.clone()),
+            DumpEntry::Raw { key, value } => (hex::encode(key), hex::encode(value)),
+        }
+    }
+
+    /// The entry's original on-disk bytes, for the `Bcs` output format. Unlike
+    /// `as_hex_pair`/`as_json`, this never goes through a decoder's string rendering,
+    /// so it round-trips even for a table with a registered schema.
+    pub fn into_raw_bytes(self) -> (Vec<u8>, Vec<u8>) {
+        match self {
+            DumpEntry::Decoded {
+                raw_key, raw_value, ..
+            } => (raw_key, raw_value),
+            DumpEntry::Raw { key, value } => (key, value),
+        }
+    }
+}
+
+/// Looks up a schema-aware decoder for `(store, table_name)`, if one has been wired up.
+/// None of sui's concrete table value types (`AuthorityPerpetualTables`,
+/// `CheckpointStore`, etc.) are visible from this crate slice, so this registry is
+/// empty and every table falls back to `DumpEntry::Raw` below; `dump_table` warns about
+/// this once per call rather than silently handing back hex for everything. Wiring a
+/// table in means adding a `(store, table_name, decode_fn)` entry that calls the
+/// table's real key/value deserializers.
+type DecodeFn = fn(&[u8], &[u8]) -> Option<(String, String)>;
+
+fn known_schema(_store: StoreName, _table_name: &str) -> Option<DecodeFn> {
+    None
+}
+
+fn decode_entry(store: StoreName, table_name: &str, key: &[u8], value: &[u8]) -> DumpEntry {
+    match known_schema(store, table_name).and_then(|decode| decode(key, value)) {
+        Some((decoded_key, decoded_value)) => DumpEntry::Decoded {
+            key: decoded_key,
+            value: decoded_value,
+            raw_key: key.to_vec(),
+            raw_value: value.to_vec(),
+        },
+        None => DumpEntry::Raw {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        },
+    }
+}
+
+pub fn list_tables(db_path: PathBuf) -> Result<Vec<String>> {
+    let mut tables = Vec::new();
+    for store in [StoreName::Perpetual, StoreName::Checkpoints] {
+        let path = store.db_path(&db_path, None);
+        if !path.exists() {
+            continue;
+        }
+        tables.extend(
+            DB::list_cf(&Options::default(), &path)
+                .with_context(|| format!("failed to list column families at {path:?}"))?,
+        );
+    }
+    tables.sort();
+    tables.dedup();
+    Ok(tables)
+}
+
+/// Dumps up to `page_size` entries from `table_name`, seeking the iterator to
+/// `start_key` (or the start of the table) and stopping at the first key `>= end_key`
+/// if one is given. Paging composes with the range: `page_number` skips whole pages
+/// within the bounded range, not from the start of the table, so repeated calls with
+/// increasing page numbers deterministically walk the same slice.
+#[allow(clippy::too_many_arguments)]
+pub fn dump_table(
+    store: StoreName,
+    epoch: Option<EpochId>,
+    db_path: PathBuf,
+    table_name: &str,
+    page_size: u16,
+    page_number: usize,
+    start_key: Option<Vec<u8>>,
+    end_key: Option<Vec<u8>>,
+) -> Result<Vec<DumpEntry>> {
+    if known_schema(store, table_name).is_none() {
+        warn!(
+            "no schema decoder registered for {table_name:?} in {store:?}; \
+             dumping raw key/value bytes instead of decoded records"
+        );
+    }
+
+    let db = store.open_read_only(&db_path, epoch)?;
+    let cf = db
+        .cf_handle(table_name)
+        .ok_or_else(|| anyhow!("no such table {table_name:?} in {store:?}"))?;
+
+    let mode = match &start_key {
+        Some(key) => IteratorMode::From(key, Direction::Forward),
+        None => IteratorMode::Start,
+    };
+    let page_size = page_size as usize;
+    let skip = page_number.saturating_mul(page_size);
+
+    let in_range = db
+        .iterator_cf(cf, mode)
+        .take_while(|item| match (item, &end_key) {
+            (Ok((key, _)), Some(end)) => key.as_ref() < end.as_slice(),
+            _ => true,
+        });
+
+    let mut entries = Vec::with_capacity(page_size);
+    for item in in_range.skip(skip).take(page_size) {
+        let (key, value) = item.context("error reading entry from column family")?;
+        entries.push(decode_entry(store, table_name, &key, &value));
+    }
+    Ok(entries)
+}
+
+pub struct TableSummary {
+    pub num_keys: u64,
+    pub key_bytes_total: u64,
+    pub value_bytes_total: u64,
+    pub key_hist: Histogram<u64>,
+    pub value_hist: Histogram<u64>,
+}
+
+pub fn table_summary(
+    store: StoreName,
+    epoch: Option<EpochId>,
+    db_path: PathBuf,
+    table_name: &str,
+) -> Result<TableSummary> {
+    let db = store.open_read_only(&db_path, epoch)?;
+    let cf = db
+        .cf_handle(table_name)
+        .ok_or_else(|| anyhow!("no such table {table_name:?} in {store:?}"))?;
+
+    let mut key_hist = Histogram::<u64>::new(3)?;
+    let mut value_hist = Histogram::<u64>::new(3)?;
+    let (mut num_keys, mut key_bytes_total, mut value_bytes_total) = (0u64, 0u64, 0u64);
+    for item in db.iterator_cf(cf, IteratorMode::Start) {
+        let (key, value) = item.context("error reading entry from column family")?;
+        num_keys += 1;
+        key_bytes_total += key.len() as u64;
+        value_bytes_total += value.len() as u64;
+        key_hist.record(key.len() as u64)?;
+        value_hist.record(value.len() as u64)?;
+    }
+    Ok(TableSummary {
+        num_keys,
+        key_bytes_total,
+        value_bytes_total,
+        key_hist,
+        value_hist,
+    })
+}
+
+/// Counts duplicate objects in the perpetual store by comparing raw value bytes, i.e.
+/// objects that were re-written with identical contents across versions.
+pub fn duplicate_objects_summary(db_path: PathBuf) -> (usize, usize, usize, usize) {
+    let Ok(db) = StoreName::Perpetual.open_read_only(&db_path, None) else {
+        return (0, 0, 0, 0);
+    };
+    let Some(cf) = db.cf_handle("objects") else {
+        return (0, 0, 0, 0);
+    };
+
+    let mut seen_values: std::collections::HashSet<Vec<u8>> = Default::default();
+    let (mut total_count, mut duplicate_count, mut total_bytes, mut duplicated_bytes) =
+        (0, 0, 0, 0);
+    for item in db.iterator_cf(cf, IteratorMode::Start) {
+        let Ok((_, value)) = item else {
+            continue;
+        };
+        total_count += 1;
+        total_bytes += value.len();
+        if !seen_values.insert(value.to_vec()) {
+            duplicate_count += 1;
+            duplicated_bytes += value.len();
+        }
+    }
+    (total_count, duplicate_count, total_bytes, duplicated_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a throwaway perpetual-store DB under the system temp dir with one column
+    // family pre-populated with `entries`, and returns the store's root path (the
+    // parent of `store/perpetual`, as `StoreName::db_path` expects).
+    fn setup_test_db(test_name: &str, cf_name: &str, entries: &[(Vec<u8>, Vec<u8>)]) -> PathBuf {
+        let db_path = std::env::temp_dir().join(format!(
+            "sui-tool-db-dump-test-{}-{test_name}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&db_path);
+        let store_path = db_path.join("store").join("perpetual");
+        std::fs::create_dir_all(&store_path).unwrap();
+
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &store_path, [cf_name]).unwrap();
+        let cf = db.cf_handle(cf_name).unwrap();
+        for (key, value) in entries {
+            db.put_cf(cf, key, value).unwrap();
+        }
+        drop(db);
+        db_path
+    }
+
+    fn keys_of(entries: &[DumpEntry]) -> Vec<u8> {
+        entries
+            .iter()
+            .map(|e| hex::decode(e.as_hex_pair().0).unwrap()[0])
+            .collect()
+    }
+
+    #[test]
+    fn dump_table_composes_start_end_key_and_paging() {
+        let entries: Vec<(Vec<u8>, Vec<u8>)> =
+            (0u8..10).map(|i| (vec![i], vec![i])).collect();
+        let db_path = setup_test_db("range", "objects", &entries);
+
+        // No bounds, first page of 3: keys 0, 1, 2.
+        let page0 =
+            dump_table(StoreName::Perpetual, None, db_path.clone(), "objects", 3, 0, None, None)
+                .unwrap();
+        assert_eq!(keys_of(&page0), vec![0, 1, 2]);
+
+        // No bounds, second page of 3: keys 3, 4, 5 - paging continues from page 0.
+        let page1 =
+            dump_table(StoreName::Perpetual, None, db_path.clone(), "objects", 3, 1, None, None)
+                .unwrap();
+        assert_eq!(keys_of(&page1), vec![3, 4, 5]);
+
+        // Bounded range [3, 7), first page of 10: only keys inside the range.
+        let bounded = dump_table(
+            StoreName::Perpetual,
+            None,
+            db_path.clone(),
+            "objects",
+            10,
+            0,
+            Some(vec![3]),
+            Some(vec![7]),
+        )
+        .unwrap();
+        assert_eq!(keys_of(&bounded), vec![3, 4, 5, 6]);
+
+        // Paging composes with the range bound rather than the whole table: second
+        // page of size 2 within [3, 7) is keys 5, 6.
+        let bounded_page1 = dump_table(
+            StoreName::Perpetual,
+            None,
+            db_path.clone(),
+            "objects",
+            2,
+            1,
+            Some(vec![3]),
+            Some(vec![7]),
+        )
+        .unwrap();
+        assert_eq!(keys_of(&bounded_page1), vec![5, 6]);
+
+        std::fs::remove_dir_all(&db_path).ok();
+    }
+
+    #[test]
+    fn dump_table_missing_table_errors() {
+        let db_path = setup_test_db("missing", "objects", &[]);
+        let result = dump_table(
+            StoreName::Perpetual,
+            None,
+            db_path.clone(),
+            "no_such_table",
+            10,
+            0,
+            None,
+            None,
+        );
+        assert!(result.is_err());
+        std::fs::remove_dir_all(&db_path).ok();
+    }
+
+    #[test]
+    fn raw_entry_format_conversions() {
+        let entry = DumpEntry::Raw {
+            key: vec![0xde, 0xad],
+            value: vec![0xbe, 0xef],
+        };
+        assert_eq!(entry.as_hex_pair(), ("dead".to_owned(), "beef".to_owned()));
+        assert_eq!(
+            entry.as_json(),
+            json!({ "key": "dead", "value": "beef" })
+        );
+
+        let entry = DumpEntry::Raw {
+            key: vec![0xde, 0xad],
+            value: vec![0xbe, 0xef],
+        };
+        assert_eq!(entry.into_raw_bytes(), (vec![0xde, 0xad], vec![0xbe, 0xef]));
+    }
+
+    #[test]
+    fn decoded_entry_into_raw_bytes_returns_original_bytes_not_rendered_strings() {
+        // A `Decoded` entry's rendered strings need not equal its on-disk bytes, e.g.
+        // this key's bytes aren't valid UTF-8 but decode to a human-readable label.
+        let entry = DumpEntry::Decoded {
+            key: "object#1".to_owned(),
+            value: "Coin<SUI>".to_owned(),
+            raw_key: vec![0xff, 0x00],
+            raw_value: vec![0x01, 0x02, 0x03],
+        };
+        assert_eq!(
+            entry.into_raw_bytes(),
+            (vec![0xff, 0x00], vec![0x01, 0x02, 0x03])
+        );
+    }
+}
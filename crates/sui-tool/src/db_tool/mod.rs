@@ -1,8 +1,11 @@
 // Copyright (c) Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
-use self::db_dump::{dump_table, duplicate_objects_summary, list_tables, table_summary, StoreName};
+use self::db_dump::{
+    dump_table, duplicate_objects_summary, list_tables, table_summary, DumpEntry, StoreName,
+};
 use clap::Parser;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use sui_core::authority::authority_store_tables::AuthorityPerpetualTables;
 use sui_core::checkpoints::CheckpointStore;
@@ -11,16 +14,57 @@ use typed_store::rocks::MetricConf;
 
 pub mod db_dump;
 
+/// Output format for `DbToolCommand::Dump`, applied by `print_all_entries` to the
+/// entries `dump_table` returns. `Debug` preserves the original pretty-printed output;
+/// `Json`, `Csv` and `Hex` emit newline-delimited records and `Bcs` emits
+/// length-prefixed binary records, so the dump can be piped into other tooling.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+#[clap(rename_all = "kebab-case")]
+pub enum DumpFormat {
+    Debug,
+    Json,
+    Csv,
+    Hex,
+    Bcs,
+}
+
+fn parse_hex_key(s: &str) -> anyhow::Result<Vec<u8>> {
+    hex::decode(s.strip_prefix("0x").unwrap_or(s))
+        .map_err(|e| anyhow::anyhow!("invalid hex key {s:?}: {e}"))
+}
+
 #[derive(Parser)]
 #[clap(rename_all = "kebab-case")]
 pub enum DbToolCommand {
     ListTables,
     Dump(Dump),
-    TableSummary(Dump),
+    TableSummary(TableSummaryArgs),
     DuplicatesSummary,
     ResetDB,
 }
 
+// A narrower argument set than `Dump`: table-summary scans the whole table to build a
+// size histogram, so it has no use for `Dump`'s paging/range/format flags. Giving it
+// its own struct means passing e.g. `--start-key` to `table-summary` is a clap error
+// instead of a flag that's silently accepted and ignored.
+#[derive(Parser)]
+#[clap(rename_all = "kebab-case")]
+pub struct TableSummaryArgs {
+    /// The type of store to summarize
+    #[clap(long = "store", value_enum)]
+    store_name: StoreName,
+    /// The name of the table to summarize
+    #[clap(long = "table-name")]
+    table_name: String,
+
+    // TODO: We should load this automatically from the system object in AuthorityPerpetualTables.
+    // This is very difficult to do right now because you can't share code between
+    // AuthorityPerpetualTables and AuthorityEpochTablesReadonly.
+    /// The epoch to use when loading AuthorityEpochTables.
+    #[clap(long = "epoch")]
+    epoch: Option<EpochId>,
+}
+
 #[derive(Parser)]
 #[clap(rename_all = "kebab-case")]
 pub struct Dump {
@@ -37,6 +81,20 @@ pub struct Dump {
     #[clap(long = "page-num")]
     page_number: usize,
 
+    /// Hex-encoded (optionally `0x`-prefixed) key to seek the iterator to, for scanning a
+    /// bounded range instead of counting pages from the start of the table.
+    // The fully qualified `Vec` keeps clap's derive from treating `Option<Vec<u8>>` as a
+    // multi-value argument of single bytes; each flag takes one hex string.
+    #[clap(long = "start-key", value_parser = parse_hex_key)]
+    start_key: Option<::std::vec::Vec<u8>>,
+    /// Hex-encoded (optionally `0x`-prefixed) key to stop the scan at; only keys
+    /// strictly less than this bound are dumped.
+    #[clap(long = "end-key", value_parser = parse_hex_key)]
+    end_key: Option<::std::vec::Vec<u8>>,
+    /// Output format for dumped records.
+    #[clap(long = "format", value_enum, default_value_t = DumpFormat::Debug)]
+    format: DumpFormat,
+
     // TODO: We should load this automatically from the system object in AuthorityPerpetualTables.
     // This is very difficult to do right now because you can't share code between
     // AuthorityPerpetualTables and AuthorityEpochTablesReadonly.
@@ -55,9 +113,12 @@ pub fn execute_db_tool_command(db_path: PathBuf, cmd: DbToolCommand) -> anyhow::
             &d.table_name,
             d.page_size,
             d.page_number,
+            d.start_key,
+            d.end_key,
+            d.format,
         ),
-        DbToolCommand::TableSummary(d) => {
-            print_db_table_summary(d.store_name, d.epoch, db_path, &d.table_name)
+        DbToolCommand::TableSummary(args) => {
+            print_db_table_summary(args.store_name, args.epoch, db_path, &args.table_name)
         }
         DbToolCommand::DuplicatesSummary => print_db_duplicates_summary(db_path),
         DbToolCommand::ResetDB => reset_db_to_genesis(&db_path),
@@ -119,6 +180,7 @@ pub fn print_db_table_summary(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn print_all_entries(
     store: StoreName,
     epoch: Option<EpochId>,
@@ -126,9 +188,54 @@ pub fn print_all_entries(
     table_name: &str,
     page_size: u16,
     page_number: usize,
+    start_key: Option<Vec<u8>>,
+    end_key: Option<Vec<u8>>,
+    format: DumpFormat,
 ) -> anyhow::Result<()> {
-    for (k, v) in dump_table(store, epoch, path, table_name, page_size, page_number)? {
-        println!("{:>100?}: {:?}", k, v);
+    let mut csv_writer = matches!(format, DumpFormat::Csv).then(|| csv::Writer::from_writer(std::io::stdout()));
+    for entry in dump_table(
+        store,
+        epoch,
+        path,
+        table_name,
+        page_size,
+        page_number,
+        start_key,
+        end_key,
+    )? {
+        match format {
+            DumpFormat::Debug => match &entry {
+                DumpEntry::Decoded { key, value, .. } => println!("{:>100?}: {:?}", key, value),
+                DumpEntry::Raw { key, value } => {
+                    println!("{:>100?}: {:?}", hex::encode(key), hex::encode(value))
+                }
+            },
+            DumpFormat::Json => println!("{}", serde_json::to_string(&entry.as_json())?),
+            DumpFormat::Csv => {
+                let (key, value) = entry.as_hex_pair();
+                csv_writer
+                    .as_mut()
+                    .expect("csv writer initialized for Csv format")
+                    .write_record([key, value])?;
+            }
+            DumpFormat::Hex => {
+                let (key, value) = entry.as_hex_pair();
+                println!("{key}: {value}");
+            }
+            // Unlike `Hex`, this writes raw length-prefixed binary records so the
+            // output can be parsed back into bytes without a text round-trip.
+            DumpFormat::Bcs => {
+                let (key, value) = entry.into_raw_bytes();
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(&(key.len() as u32).to_le_bytes())?;
+                stdout.write_all(&key)?;
+                stdout.write_all(&(value.len() as u32).to_le_bytes())?;
+                stdout.write_all(&value)?;
+            }
+        }
+    }
+    if let Some(mut writer) = csv_writer {
+        writer.flush()?;
     }
     Ok(())
 }
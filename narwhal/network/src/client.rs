@@ -7,7 +7,7 @@ use anemo::{PeerId, Request};
 use async_trait::async_trait;
 use crypto::{traits::KeyPair, NetworkKeyPair, NetworkPublicKey};
 use parking_lot::RwLock;
-use tokio::time::sleep;
+use tokio::{sync::Notify, time::sleep};
 use tracing::debug;
 use types::{
     error::LocalClientError, PrimaryToWorker, WorkerOthersBatchMessage, WorkerOurBatchMessage,
@@ -16,9 +16,22 @@ use types::{
 
 use crate::traits::{PrimaryToOwnWorkerClient, WorkerToOwnPrimaryClient};
 
+// Total time a getter will wait for its handler to be registered before giving up, absent
+// an explicit override via `NetworkClient::with_handler_wait_timeout`. Callers are woken
+// immediately on registration rather than polling, so this is only a worst-case bound for
+// a handler that never shows up.
+const DEFAULT_HANDLER_WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Clone)]
 pub struct NetworkClient {
     inner: Arc<RwLock<Inner>>,
+    // Notified whenever a handler is registered, so getters can wake up immediately
+    // instead of polling. These live outside `Inner` so they survive `shutdown()`
+    // replacing it.
+    primary_to_own_worker_notify: Arc<Notify>,
+    worker_to_own_primary_notify: Arc<Notify>,
+    worker_to_own_worker_notify: Arc<Notify>,
+    handler_wait_timeout: Duration,
 }
 
 struct Inner {
@@ -40,6 +53,10 @@ impl NetworkClient {
                 worker_to_own_worker_handler: BTreeMap::new(),
                 shutdown: false,
             })),
+            primary_to_own_worker_notify: Arc::new(Notify::new()),
+            worker_to_own_primary_notify: Arc::new(Notify::new()),
+            worker_to_own_worker_notify: Arc::new(Notify::new()),
+            handler_wait_timeout: DEFAULT_HANDLER_WAIT_TIMEOUT,
         }
     }
 
@@ -52,9 +69,16 @@ impl NetworkClient {
         Self::new(empty_peer_id())
     }
 
+    pub fn with_handler_wait_timeout(mut self, timeout: Duration) -> Self {
+        self.handler_wait_timeout = timeout;
+        self
+    }
+
     pub fn set_worker_to_primary_local_handler(&self, handler: Arc<dyn WorkerToPrimary>) {
         let mut inner = self.inner.write();
         inner.worker_to_primary_handler = Some(handler);
+        drop(inner);
+        self.worker_to_own_primary_notify.notify_waiters();
     }
 
     pub fn set_primary_to_worker_local_handler(
@@ -66,6 +90,8 @@ impl NetworkClient {
         inner
             .primary_to_own_worker_handler
             .insert(worker_id, handler);
+        drop(inner);
+        self.primary_to_own_worker_notify.notify_waiters();
     }
 
     pub fn set_worker_to_worker_local_handler(
@@ -77,6 +103,8 @@ impl NetworkClient {
         inner
             .worker_to_own_worker_handler
             .insert(worker_id, handler);
+        drop(inner);
+        self.worker_to_own_worker_notify.notify_waiters();
     }
 
     pub fn shutdown(&self) {
@@ -92,13 +120,26 @@ impl NetworkClient {
             worker_to_own_worker_handler: BTreeMap::new(),
             shutdown: true,
         };
+        drop(inner);
+        // Wake up any getters so they observe the shutdown flag instead of waiting
+        // out the full timeout.
+        self.primary_to_own_worker_notify.notify_waiters();
+        self.worker_to_own_primary_notify.notify_waiters();
+        self.worker_to_own_worker_notify.notify_waiters();
     }
 
     async fn get_primary_to_own_worker_handler(
         &self,
         peer_id: PeerId,
     ) -> Result<Arc<dyn PrimaryToWorker>, LocalClientError> {
-        for _ in 0..10 {
+        let deadline = sleep(self.handler_wait_timeout);
+        tokio::pin!(deadline);
+        loop {
+            // Register interest before checking the map, so a registration that races
+            // with this check (insert + notify_waiters happening right after we read
+            // the map) still wakes us on the next `notified` poll instead of being
+            // missed until the timeout.
+            let notified = self.primary_to_own_worker_notify.notified();
             {
                 let inner = self.inner.read();
                 if inner.shutdown {
@@ -108,15 +149,22 @@ impl NetworkClient {
                     return Ok(handler.clone());
                 }
             }
-            sleep(Duration::from_millis(500)).await;
+            tokio::select! {
+                _ = notified => {}
+                _ = &mut deadline => {
+                    return Err(LocalClientError::WorkerNotStarted(peer_id));
+                }
+            }
         }
-        Err(LocalClientError::WorkerNotStarted(peer_id))
     }
 
     async fn get_worker_to_own_primary_handler(
         &self,
     ) -> Result<Arc<dyn WorkerToPrimary>, LocalClientError> {
-        for _ in 0..10 {
+        let deadline = sleep(self.handler_wait_timeout);
+        tokio::pin!(deadline);
+        loop {
+            let notified = self.worker_to_own_primary_notify.notified();
             {
                 let inner = self.inner.read();
                 if inner.shutdown {
@@ -127,18 +175,25 @@ impl NetworkClient {
                     return Ok(handler.clone());
                 }
             }
-            sleep(Duration::from_millis(500)).await;
+            tokio::select! {
+                _ = notified => {}
+                _ = &mut deadline => {
+                    return Err(LocalClientError::PrimaryNotStarted(
+                        self.inner.read().primary_peer_id,
+                    ));
+                }
+            }
         }
-        Err(LocalClientError::PrimaryNotStarted(
-            self.inner.read().primary_peer_id,
-        ))
     }
 
     async fn _get_own_worker_to_worker_handler(
         &self,
         peer_id: PeerId,
     ) -> Result<Arc<dyn WorkerToWorker>, LocalClientError> {
-        for _ in 0..10 {
+        let deadline = sleep(self.handler_wait_timeout);
+        tokio::pin!(deadline);
+        loop {
+            let notified = self.worker_to_own_worker_notify.notified();
             {
                 let inner = self.inner.read();
                 if inner.shutdown {
@@ -148,9 +203,13 @@ impl NetworkClient {
                     return Ok(handler.clone());
                 }
             }
-            sleep(Duration::from_millis(500)).await;
+            tokio::select! {
+                _ = notified => {}
+                _ = &mut deadline => {
+                    return Err(LocalClientError::WorkerNotStarted(peer_id));
+                }
+            }
         }
-        Err(LocalClientError::WorkerNotStarted(peer_id))
     }
 }
 
@@ -199,3 +258,79 @@ impl WorkerToOwnPrimaryClient for NetworkClient {
 fn empty_peer_id() -> PeerId {
     PeerId([0u8; 32])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockWorkerToPrimary;
+
+    #[async_trait]
+    impl WorkerToPrimary for MockWorkerToPrimary {
+        async fn report_our_batch(
+            &self,
+            _request: Request<WorkerOurBatchMessage>,
+        ) -> Result<anemo::Response<()>, anemo::rpc::Status> {
+            Ok(anemo::Response::new(()))
+        }
+
+        async fn report_others_batch(
+            &self,
+            _request: Request<WorkerOthersBatchMessage>,
+        ) -> Result<anemo::Response<()>, anemo::rpc::Status> {
+            Ok(anemo::Response::new(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn getter_wakes_immediately_on_late_registration_instead_of_blocking_for_full_timeout() {
+        let client = NetworkClient::new_with_empty_id()
+            .with_handler_wait_timeout(Duration::from_secs(60));
+
+        let waiter = {
+            let client = client.clone();
+            tokio::spawn(async move { client.get_worker_to_own_primary_handler().await })
+        };
+
+        // Give the getter a chance to find the map empty and start waiting before the
+        // handler shows up, so the result comes from the notify wakeup rather than
+        // from the first map check.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.set_worker_to_primary_local_handler(Arc::new(MockWorkerToPrimary));
+
+        let result = tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .expect("getter should have woken on registration, not waited out the 60s timeout")
+            .unwrap();
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn getter_returns_shutting_down_error_once_shutdown_is_called() {
+        let client =
+            NetworkClient::new_with_empty_id().with_handler_wait_timeout(Duration::from_secs(60));
+
+        let waiter = {
+            let client = client.clone();
+            tokio::spawn(async move { client.get_worker_to_own_primary_handler().await })
+        };
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.shutdown();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .expect("getter should wake on shutdown, not wait out the 60s timeout")
+            .unwrap();
+        assert!(matches!(result, Err(LocalClientError::ShuttingDown)));
+    }
+
+    #[tokio::test]
+    async fn getter_returns_shutting_down_error_if_already_shut_down() {
+        let client = NetworkClient::new_with_empty_id();
+        client.shutdown();
+
+        let result = client.get_worker_to_own_primary_handler().await;
+        assert!(matches!(result, Err(LocalClientError::ShuttingDown)));
+    }
+}